@@ -1,214 +1,541 @@
-use std::cell::RefCell;
-use std::rc::{Rc, Weak};
+use std::collections::{HashMap, HashSet, VecDeque};
 
-enum SupplyType {
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SupplyType {
     Core(String),
     Neutral,
 }
 
-enum LandType {
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LandType {
     Normal,
     SupplyCenter(SupplyType),
+    /// A province with multiple named coasts (e.g. Spain's north and south
+    /// coasts), each of which is modeled as its own sub-node linked back to
+    /// this one via `Node::parent`.
+    Coasts(Vec<String>),
 }
 
-enum TerritoryType {
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TerritoryType {
     Sea,
     Land(LandType),
 }
 
+/// Which kind of unit may cross an edge: armies cross land↔land borders,
+/// fleets cross sea↔sea and sea↔coastal-land borders, and `Both` marks a
+/// border that either kind of unit may cross.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MoveKind {
+    Army,
+    Fleet,
+    Both,
+}
+
+impl MoveKind {
+    fn allows(self, unit: MoveKind) -> bool {
+        self == MoveKind::Both || self == unit
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 struct Node {
     name: String,
     attributes: TerritoryType,
-    neighbors: Vec<NodeWeakRef>,
+    neighbors: HashMap<NodeId, MoveKind>,
+    /// For a coast sub-node, the whole-province land node it belongs to.
+    /// `None` for ordinary provinces and for parent nodes themselves.
+    parent: Option<NodeId>,
+    occupied: bool,
 }
 
 impl Node {
     fn new(name: String, attributes: TerritoryType) -> Self {
-        Node{
+        Node {
             name,
             attributes,
-            neighbors: Vec::new(),
+            neighbors: HashMap::new(),
+            parent: None,
+            occupied: false,
         }
     }
 }
 
-type NodeRef = Rc<RefCell<Node>>;
-type NodeWeakRef = Weak<RefCell<Node>>;
+/// A stable handle to a node in a `Graph`. Remains valid across `remove_node`
+/// calls for every other node, since removal tombstones a slot rather than
+/// shifting the arena.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NodeId(usize);
+
+/// An arena slot: either a live node or the tombstone left behind by
+/// `remove_node`, which keeps every other node's `NodeId` valid.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+enum Slot {
+    Occupied(Node),
+    Tombstone,
+}
+
+/// Returned by `insert_named` when a province name is already registered.
+#[derive(Debug, PartialEq, Eq)]
+pub struct KeyAlreadyExists(String);
+
+/// Returned by `add_edge_by_name` when one of the named endpoints isn't in
+/// the graph.
+#[derive(Debug, PartialEq, Eq)]
+pub enum InsertEdgeError {
+    NoSuchNode(String),
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Graph {
+    nodes: Vec<Slot>,
+    indices: HashMap<String, NodeId>,
+}
 
-struct Graph {
-    nodes: Vec<NodeRef>,
+impl Default for Graph {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Graph {
-    fn new() -> Self {
-        Graph { nodes: Vec::new() }
+    pub fn new() -> Self {
+        Graph {
+            nodes: Vec::new(),
+            indices: HashMap::new(),
+        }
     }
 }
 
 impl Graph {
-    // Add a new node to the graph and return a strong reference to it
-    fn add_node(&mut self, node: Node) -> NodeRef {
-        let node = Rc::new(RefCell::new(node));
-        self.nodes.push(node.clone());
-        node
+    /// Look up a node by id, ignoring tombstoned slots.
+    fn get(&self, id: NodeId) -> Option<&Node> {
+        match self.nodes.get(id.0) {
+            Some(Slot::Occupied(node)) => Some(node),
+            _ => None,
+        }
     }
 
-    // Remove a node from the graph
-    fn remove_node(&mut self, node_ref: &NodeRef) {
-        // Remove the node from the graph's node list
-        self.nodes.retain(|n| !Rc::ptr_eq(n, node_ref));
+    /// Look up a node by id, ignoring tombstoned slots.
+    fn get_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        match self.nodes.get_mut(id.0) {
+            Some(Slot::Occupied(node)) => Some(node),
+            _ => None,
+        }
+    }
 
-        // Remove the node from neighbors' lists
-        for node in &self.nodes {
-            node.borrow_mut().neighbors.retain(|neighbor_weak| {
-                neighbor_weak.upgrade().map_or(false, |neighbor_strong| {
-                    !Rc::ptr_eq(&neighbor_strong, node_ref)
-                })
-            });
+    // Add a new node to the graph and return its id
+    fn add_node(&mut self, node: Node) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.indices.insert(node.name.clone(), id);
+        self.nodes.push(Slot::Occupied(node));
+        id
+    }
+
+    /// Register a node under `name`, erroring instead of overwriting if that
+    /// name is already taken by another node.
+    pub fn insert_named(
+        &mut self,
+        name: impl Into<String>,
+        attributes: TerritoryType,
+    ) -> Result<NodeId, KeyAlreadyExists> {
+        let name = name.into();
+        if self.indices.contains_key(&name) {
+            return Err(KeyAlreadyExists(name));
         }
+        Ok(self.add_node(Node::new(name, attributes)))
     }
 
-    // Add an edge between two nodes
-    fn add_edge(&self, node1: &NodeRef, node2: &NodeRef) {
-        node1.borrow_mut().neighbors.push(Rc::downgrade(node2));
-        node2.borrow_mut().neighbors.push(Rc::downgrade(node1)); // For undirected graphs
+    /// Look up a node's id by its province name.
+    pub fn node_by_name(&self, name: &str) -> Option<NodeId> {
+        self.indices.get(name).copied()
     }
 
-    // Remove an edge between two nodes
-    fn remove_edge(&self, node1: &NodeRef, node2: &NodeRef) {
-        node1.borrow_mut().neighbors.retain(|neighbor_weak| {
-            neighbor_weak.upgrade().map_or(false, |neighbor_strong| {
-                !Rc::ptr_eq(&neighbor_strong, node2)
-            })
-        });
-        node2.borrow_mut().neighbors.retain(|neighbor_weak| {
-            neighbor_weak.upgrade().map_or(false, |neighbor_strong| {
-                !Rc::ptr_eq(&neighbor_strong, node1)
+    /// Register a coast as its own node, linked back to `parent` (the
+    /// whole-province land node) for army adjacency and occupation purposes.
+    /// Private: callers go through `insert_declared_coasts` so the coast
+    /// nodes that exist can never drift from what `parent` declares.
+    fn insert_coast(
+        &mut self,
+        parent: NodeId,
+        name: impl Into<String>,
+    ) -> Result<NodeId, KeyAlreadyExists> {
+        let id = self.insert_named(name, TerritoryType::Land(LandType::Normal))?;
+        if let Some(node) = self.get_mut(id) {
+            node.parent = Some(parent);
+        }
+        Ok(id)
+    }
+
+    /// Create one coast sub-node for every name in `parent`'s declared
+    /// `LandType::Coasts(..)` list, in order. Returns an empty vec if
+    /// `parent` isn't a `Coasts` province (or doesn't exist), so a map
+    /// author can never register a coast whose name isn't in the
+    /// province's own declared list, or declare coasts that never get a
+    /// node.
+    pub fn insert_declared_coasts(
+        &mut self,
+        parent: NodeId,
+    ) -> Result<Vec<NodeId>, KeyAlreadyExists> {
+        let names = match self.get(parent).map(|node| &node.attributes) {
+            Some(TerritoryType::Land(LandType::Coasts(names))) => names.clone(),
+            _ => Vec::new(),
+        };
+
+        names
+            .into_iter()
+            .map(|name| self.insert_coast(parent, name))
+            .collect()
+    }
+
+    /// The whole-province land node a coast sub-node belongs to, if `id` is
+    /// a coast.
+    pub fn parent_province(&self, id: NodeId) -> Option<NodeId> {
+        self.get(id)?.parent
+    }
+
+    /// The coast sub-nodes linked to the given parent land node.
+    pub fn coasts_of(&self, id: NodeId) -> Vec<NodeId> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, slot)| match slot {
+                Slot::Occupied(node) if node.parent == Some(id) => Some(NodeId(idx)),
+                _ => None,
             })
-        });
+            .collect()
+    }
+
+    /// Mark a node occupied or vacated. Occupying a coast also marks its
+    /// parent province occupied, since the two share a supply center for
+    /// support and ownership purposes.
+    pub fn set_occupied(&mut self, id: NodeId, occupied: bool) {
+        let parent = self.get(id).and_then(|node| node.parent);
+
+        if let Some(node) = self.get_mut(id) {
+            node.occupied = occupied;
+        }
+        if let Some(parent_id) = parent {
+            if let Some(parent_node) = self.get_mut(parent_id) {
+                parent_node.occupied = occupied;
+            }
+        }
+    }
+
+    pub fn is_occupied(&self, id: NodeId) -> bool {
+        self.get(id).is_some_and(|node| node.occupied)
+    }
+
+    /// Render the map as Graphviz DOT, coloring nodes by territory type and
+    /// labeling supply centers with their owning power, so a map author can
+    /// eyeball adjacency correctness.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("graph diplomacy_map {\n");
+
+        for (idx, slot) in self.nodes.iter().enumerate() {
+            if let Slot::Occupied(node) = slot {
+                let (color, label) = Self::dot_style(node);
+                out.push_str(&format!(
+                    "    n{} [label=\"{}\", style=filled, fillcolor=\"{}\"];\n",
+                    idx, label, color
+                ));
+            }
+        }
+        out.push('\n');
+
+        let mut seen_edges: HashSet<(usize, usize)> = HashSet::new();
+        for (idx, slot) in self.nodes.iter().enumerate() {
+            if let Slot::Occupied(node) = slot {
+                for &neighbor in node.neighbors.keys() {
+                    let edge = if idx < neighbor.0 {
+                        (idx, neighbor.0)
+                    } else {
+                        (neighbor.0, idx)
+                    };
+                    if seen_edges.insert(edge) {
+                        out.push_str(&format!("    n{} -- n{};\n", edge.0, edge.1));
+                    }
+                }
+            }
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    fn dot_style(node: &Node) -> (&'static str, String) {
+        match &node.attributes {
+            TerritoryType::Sea => ("lightblue", node.name.clone()),
+            TerritoryType::Land(LandType::Normal) => ("lightgray", node.name.clone()),
+            TerritoryType::Land(LandType::Coasts(_)) => ("lightgreen", node.name.clone()),
+            TerritoryType::Land(LandType::SupplyCenter(SupplyType::Core(power))) => {
+                ("khaki", format!("{}\\n({})", node.name, power))
+            }
+            TerritoryType::Land(LandType::SupplyCenter(SupplyType::Neutral)) => {
+                ("khaki", format!("{}\\n(neutral)", node.name))
+            }
+        }
+    }
+
+    /// Add an edge between two nodes named by their province names.
+    pub fn add_edge_by_name(
+        &mut self,
+        name1: &str,
+        name2: &str,
+        kind: MoveKind,
+    ) -> Result<(), InsertEdgeError> {
+        let id1 = self
+            .node_by_name(name1)
+            .ok_or_else(|| InsertEdgeError::NoSuchNode(name1.to_string()))?;
+        let id2 = self
+            .node_by_name(name2)
+            .ok_or_else(|| InsertEdgeError::NoSuchNode(name2.to_string()))?;
+        self.add_edge(id1, id2, kind);
+        Ok(())
+    }
+
+    // Remove a node from the graph, tombstoning its slot so every other
+    // node's NodeId stays valid.
+    pub fn remove_node(&mut self, id: NodeId) {
+        let neighbors = match self.get(id) {
+            Some(node) => node.neighbors.clone(),
+            None => return,
+        };
+
+        for neighbor_id in neighbors.into_keys() {
+            if let Some(neighbor) = self.get_mut(neighbor_id) {
+                neighbor.neighbors.remove(&id);
+            }
+        }
+
+        // Scrub the parent back-reference from any coasts of this node, so
+        // they don't point at a tombstoned parent once it's gone.
+        for coast_id in self.coasts_of(id) {
+            if let Some(coast) = self.get_mut(coast_id) {
+                coast.parent = None;
+            }
+        }
+
+        if let Some(node) = self.get(id) {
+            let name = node.name.clone();
+            self.indices.remove(&name);
+        }
+
+        if let Some(slot) = self.nodes.get_mut(id.0) {
+            *slot = Slot::Tombstone;
+        }
+    }
+
+    // Add an edge between two nodes, typed by which unit may cross it
+    pub fn add_edge(&mut self, node1: NodeId, node2: NodeId, kind: MoveKind) {
+        if let Some(node) = self.get_mut(node1) {
+            node.neighbors.insert(node2, kind);
+        }
+        if let Some(node) = self.get_mut(node2) {
+            node.neighbors.insert(node1, kind); // For undirected graphs
+        }
+    }
+
+    // Remove an edge between two nodes
+    pub fn remove_edge(&mut self, node1: NodeId, node2: NodeId) {
+        if let Some(node) = self.get_mut(node1) {
+            node.neighbors.remove(&node2);
+        }
+        if let Some(node) = self.get_mut(node2) {
+            node.neighbors.remove(&node1);
+        }
+    }
+
+    /// Neighbors of `id` that a unit of the given kind may cross into.
+    pub fn neighbors_for(&self, id: NodeId, kind: MoveKind) -> Vec<NodeId> {
+        match self.get(id) {
+            Some(node) => node
+                .neighbors
+                .iter()
+                .filter(|(_, &edge_kind)| edge_kind.allows(kind))
+                .map(|(&neighbor, _)| neighbor)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Movement distance (in hops) from `from` to every province reachable
+    /// by a unit of the given kind, via unit-weight BFS over the typed
+    /// adjacency. An army's distances never cross seas; a fleet's never
+    /// cross interior land.
+    pub fn distances_from(&self, from: NodeId, kind: MoveKind) -> HashMap<NodeId, usize> {
+        let mut distances = HashMap::new();
+        if self.get(from).is_none() {
+            return distances;
+        }
+
+        distances.insert(from, 0);
+        let mut queue = VecDeque::new();
+        queue.push_back(from);
+
+        while let Some(current) = queue.pop_front() {
+            let current_distance = distances[&current];
+            for next in self.neighbors_for(current, kind) {
+                if let std::collections::hash_map::Entry::Vacant(entry) = distances.entry(next) {
+                    entry.insert(current_distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        distances
+    }
+
+    /// Movement distance from `from` to `to` for the given unit kind, or
+    /// `None` if no route exists (e.g. a landlocked province for a fleet).
+    pub fn distance(&self, from: NodeId, to: NodeId, kind: MoveKind) -> Option<usize> {
+        self.distances_from(from, kind).get(&to).copied()
+    }
+
+    /// Find the shortest chain of convoying seas carrying an army from
+    /// `from` to `to`, both of which must be land provinces. Only sea nodes
+    /// present in `convoying_seas` may be used as intermediate hops; no
+    /// other land node may be entered along the way.
+    pub fn convoy_path(
+        &self,
+        from: NodeId,
+        to: NodeId,
+        convoying_seas: &HashSet<NodeId>,
+    ) -> Option<Vec<NodeId>> {
+        if !matches!(self.get(from)?.attributes, TerritoryType::Land(_))
+            || !matches!(self.get(to)?.attributes, TerritoryType::Land(_))
+        {
+            return None;
+        }
+
+        let is_convoying_sea = |id: NodeId| {
+            convoying_seas.contains(&id)
+                && matches!(self.get(id).map(|node| &node.attributes), Some(TerritoryType::Sea))
+        };
+
+        let mut visited: HashSet<NodeId> = HashSet::new();
+        let mut prev: HashMap<NodeId, NodeId> = HashMap::new();
+        let mut queue: VecDeque<NodeId> = VecDeque::new();
+
+        visited.insert(from);
+        queue.push_back(from);
+
+        while let Some(current) = queue.pop_front() {
+            for next in self.neighbors_for(current, MoveKind::Fleet) {
+                if visited.contains(&next) {
+                    continue;
+                }
+
+                if next == to {
+                    // The final hop onto dry land must come from a
+                    // convoying sea; a bare land border isn't a convoy.
+                    if !is_convoying_sea(current) {
+                        continue;
+                    }
+                } else if !is_convoying_sea(next) {
+                    continue;
+                }
+
+                visited.insert(next);
+                prev.insert(next, current);
+
+                if next == to {
+                    let mut path = vec![next];
+                    let mut node = next;
+                    while let Some(&p) = prev.get(&node) {
+                        path.push(p);
+                        node = p;
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+
+                queue.push_back(next);
+            }
+        }
+
+        None
     }
 }
 
+#[cfg(test)]
 fn gen_test_turkey() -> Graph {
     let mut g = Graph::new();
 
-    let con = Node::new(
-        "Constantinople".to_string(),
-        TerritoryType::Land(
-            LandType::SupplyCenter(
-                SupplyType::Core("Turkey".to_string()))
-        )
-    );
-
-    let ank = Node::new(
-        "Ankara".to_string(),
-        TerritoryType::Land(
-            LandType::SupplyCenter(
-                SupplyType::Core("Turkey".to_string()))
-        )
-    );
-
-    let smy = Node::new(
-        "Smyrna".to_string(),
-        TerritoryType::Land(
-            LandType::SupplyCenter(
-                SupplyType::Core("Turkey".to_string()))
-        )
-    );
-
-    let con = g.add_node(con);
-    let smy = g.add_node(smy);
-    let ank = g.add_node(ank);
-
-    g.add_edge(&con, &ank);
-    g.add_edge(&con, &smy);
-    g.add_edge(&ank, &smy);
+    g.insert_named(
+        "Constantinople",
+        TerritoryType::Land(LandType::SupplyCenter(SupplyType::Core("Turkey".to_string()))),
+    ).unwrap();
+
+    g.insert_named(
+        "Ankara",
+        TerritoryType::Land(LandType::SupplyCenter(SupplyType::Core("Turkey".to_string()))),
+    ).unwrap();
+
+    g.insert_named(
+        "Smyrna",
+        TerritoryType::Land(LandType::SupplyCenter(SupplyType::Core("Turkey".to_string()))),
+    ).unwrap();
+
+    g.add_edge_by_name("Constantinople", "Ankara", MoveKind::Army).unwrap();
+    g.add_edge_by_name("Constantinople", "Smyrna", MoveKind::Army).unwrap();
+    g.add_edge_by_name("Ankara", "Smyrna", MoveKind::Army).unwrap();
 
     g
 }
 
+#[cfg(test)]
 fn gen_test_turkey_region() -> Graph {
     let mut g = Graph::new();
 
-    let con = Node::new(
-        "Constantinople".to_string(),
-        TerritoryType::Land(
-            LandType::SupplyCenter(
-                SupplyType::Core("Turkey".to_string()))
-        )
-    );
-
-    let ank = Node::new(
-        "Ankara".to_string(),
-        TerritoryType::Land(
-            LandType::SupplyCenter(
-                SupplyType::Core("Turkey".to_string()))
-        )
-    );
-
-    let smy = Node::new(
-        "Smyrna".to_string(),
-        TerritoryType::Land(
-            LandType::SupplyCenter(
-                SupplyType::Core("Turkey".to_string()))
-        )
-    );
-
-    let sev = Node::new(
-        "Sevastopol".to_string(),
-        TerritoryType::Land(
-            LandType::SupplyCenter(
-                SupplyType::Core("Russia".to_string()))
-        )
-    );
-
-    let bla = Node::new(
-        "Black Sea".to_string(),
-        TerritoryType::Sea
-    );
-
-    let eas = Node::new(
-        "Eastern Mediterranean".to_string(),
-        TerritoryType::Sea
-    );
-
-    let arm = Node::new(
-        "Armenia".to_string(),
-        TerritoryType::Land(LandType::Normal)
-    );
-
-    let syr = Node::new(
-        "Syria".to_string(),
-        TerritoryType::Land(LandType::Normal)
-    );
-
-    let con = g.add_node(con);
-    let smy = g.add_node(smy);
-    let ank = g.add_node(ank);
-    let sev = g.add_node(sev);
-    let bla = g.add_node(bla);
-    let eas = g.add_node(eas);
-    let arm = g.add_node(arm);
-    let syr = g.add_node(syr);
-
-    g.add_edge(&con, &ank);
-    g.add_edge(&con, &smy);
-    g.add_edge(&con, &bla);
-
-    g.add_edge(&ank, &bla);
-    g.add_edge(&ank, &smy);
-    g.add_edge(&ank, &arm);
-
-    g.add_edge(&smy, &eas);
-    g.add_edge(&smy, &arm);
-    g.add_edge(&smy, &syr);
-
-    g.add_edge(&sev, &bla);
-    g.add_edge(&sev, &arm);
-
-    g.add_edge(&eas, &syr);
-    g.add_edge(&bla, &arm);
-    g.add_edge(&arm, &syr);
+    g.insert_named(
+        "Constantinople",
+        TerritoryType::Land(LandType::SupplyCenter(SupplyType::Core("Turkey".to_string()))),
+    ).unwrap();
+
+    g.insert_named(
+        "Ankara",
+        TerritoryType::Land(LandType::SupplyCenter(SupplyType::Core("Turkey".to_string()))),
+    ).unwrap();
+
+    g.insert_named(
+        "Smyrna",
+        TerritoryType::Land(LandType::SupplyCenter(SupplyType::Core("Turkey".to_string()))),
+    ).unwrap();
+
+    g.insert_named(
+        "Sevastopol",
+        TerritoryType::Land(LandType::SupplyCenter(SupplyType::Core("Russia".to_string()))),
+    ).unwrap();
+
+    g.insert_named("Black Sea", TerritoryType::Sea).unwrap();
+    g.insert_named("Eastern Mediterranean", TerritoryType::Sea).unwrap();
+    g.insert_named("Armenia", TerritoryType::Land(LandType::Normal)).unwrap();
+    g.insert_named("Syria", TerritoryType::Land(LandType::Normal)).unwrap();
+
+    g.add_edge_by_name("Constantinople", "Ankara", MoveKind::Army).unwrap();
+    g.add_edge_by_name("Constantinople", "Smyrna", MoveKind::Army).unwrap();
+    g.add_edge_by_name("Constantinople", "Black Sea", MoveKind::Fleet).unwrap();
+
+    g.add_edge_by_name("Ankara", "Black Sea", MoveKind::Fleet).unwrap();
+    g.add_edge_by_name("Ankara", "Smyrna", MoveKind::Army).unwrap();
+    g.add_edge_by_name("Ankara", "Armenia", MoveKind::Army).unwrap();
+
+    g.add_edge_by_name("Smyrna", "Eastern Mediterranean", MoveKind::Fleet).unwrap();
+    g.add_edge_by_name("Smyrna", "Armenia", MoveKind::Army).unwrap();
+    g.add_edge_by_name("Smyrna", "Syria", MoveKind::Army).unwrap();
+
+    g.add_edge_by_name("Sevastopol", "Black Sea", MoveKind::Fleet).unwrap();
+    g.add_edge_by_name("Sevastopol", "Armenia", MoveKind::Army).unwrap();
+
+    g.add_edge_by_name("Eastern Mediterranean", "Syria", MoveKind::Fleet).unwrap();
+    g.add_edge_by_name("Black Sea", "Armenia", MoveKind::Fleet).unwrap();
+    g.add_edge_by_name("Armenia", "Syria", MoveKind::Army).unwrap();
 
     g
 }
@@ -217,18 +544,20 @@ fn gen_test_turkey_region() -> Graph {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashMap;
 
     #[test]
     fn test_turkey_nodes() {
         let turkey = gen_test_turkey();
 
-        // Build a map from node names to NodeRefs for easy access
-        let mut node_map: HashMap<String, NodeRef> = HashMap::new();
-        for node_ref in &turkey.nodes {
-            let node = node_ref.borrow();
-            node_map.insert(node.name.clone(), Rc::clone(node_ref));
-        }
+        // Collect the live (non-tombstoned) node names.
+        let names: HashSet<&str> = turkey
+            .nodes
+            .iter()
+            .filter_map(|slot| match slot {
+                Slot::Occupied(node) => Some(node.name.as_str()),
+                Slot::Tombstone => None,
+            })
+            .collect();
 
         // Check that all expected nodes are present
         let expected_nodes = vec![
@@ -236,14 +565,217 @@ mod tests {
             "Ankara",
             "Smyrna",
         ];
-        assert_eq!(node_map.len(), expected_nodes.len());
+        assert_eq!(names.len(), expected_nodes.len());
 
         for name in &expected_nodes {
             assert!(
-                node_map.contains_key(*name),
+                names.contains(*name),
                 "Node '{}' is missing from the graph",
                 name
             );
         }
     }
+
+    #[test]
+    fn test_remove_node_tombstones_slot_and_scrubs_neighbors() {
+        let mut g = Graph::new();
+        let ank = g.insert_named("Ankara", TerritoryType::Sea).unwrap();
+        let con = g.insert_named("Constantinople", TerritoryType::Sea).unwrap();
+        let smy = g.insert_named("Smyrna", TerritoryType::Sea).unwrap();
+        g.add_edge(ank, con, MoveKind::Both);
+        g.add_edge(ank, smy, MoveKind::Both);
+
+        g.remove_node(ank);
+
+        // The tombstoned id is gone...
+        assert!(g.get(ank).is_none());
+        assert_eq!(g.node_by_name("Ankara"), None);
+
+        // ...and so is every reference to it from its former neighbors,
+        // while their own ids and names stay valid.
+        assert!(!g.get(con).unwrap().neighbors.contains_key(&ank));
+        assert!(!g.get(smy).unwrap().neighbors.contains_key(&ank));
+        assert_eq!(g.node_by_name("Constantinople"), Some(con));
+        assert_eq!(g.node_by_name("Smyrna"), Some(smy));
+    }
+
+    #[test]
+    fn test_insert_named_rejects_duplicates() {
+        let mut g = Graph::new();
+        g.insert_named("Constantinople", TerritoryType::Sea).unwrap();
+
+        let err = g
+            .insert_named("Constantinople", TerritoryType::Sea)
+            .unwrap_err();
+        assert_eq!(err, KeyAlreadyExists("Constantinople".to_string()));
+    }
+
+    #[test]
+    fn test_add_edge_by_name_reports_missing_node() {
+        let mut g = Graph::new();
+        g.insert_named("Ankara", TerritoryType::Sea).unwrap();
+
+        let err = g
+            .add_edge_by_name("Ankara", "Nowhere", MoveKind::Army)
+            .unwrap_err();
+        assert_eq!(err, InsertEdgeError::NoSuchNode("Nowhere".to_string()));
+    }
+
+    #[test]
+    fn test_convoy_path_crosses_convoying_sea() {
+        let g = gen_test_turkey_region();
+        let con = g.node_by_name("Constantinople").unwrap();
+        let sev = g.node_by_name("Sevastopol").unwrap();
+        let bla = g.node_by_name("Black Sea").unwrap();
+
+        let convoying_seas: HashSet<NodeId> = [bla].into_iter().collect();
+        let path = g.convoy_path(con, sev, &convoying_seas).unwrap();
+        assert_eq!(path, vec![con, bla, sev]);
+    }
+
+    #[test]
+    fn test_convoy_path_fails_without_a_convoying_fleet() {
+        let g = gen_test_turkey_region();
+        let con = g.node_by_name("Constantinople").unwrap();
+        let sev = g.node_by_name("Sevastopol").unwrap();
+
+        assert_eq!(g.convoy_path(con, sev, &HashSet::new()), None);
+    }
+
+    #[test]
+    fn test_neighbors_for_filters_by_move_kind() {
+        let g = gen_test_turkey_region();
+        let con = g.node_by_name("Constantinople").unwrap();
+        let ank = g.node_by_name("Ankara").unwrap();
+        let bla = g.node_by_name("Black Sea").unwrap();
+
+        let army_neighbors: HashSet<NodeId> =
+            g.neighbors_for(con, MoveKind::Army).into_iter().collect();
+        assert!(army_neighbors.contains(&ank));
+        assert!(!army_neighbors.contains(&bla));
+
+        let fleet_neighbors: HashSet<NodeId> =
+            g.neighbors_for(con, MoveKind::Fleet).into_iter().collect();
+        assert!(fleet_neighbors.contains(&bla));
+        assert!(!fleet_neighbors.contains(&ank));
+    }
+
+    #[test]
+    fn test_occupying_a_coast_marks_its_parent_occupied() {
+        let mut g = Graph::new();
+        let spain = g
+            .insert_named(
+                "Spain",
+                TerritoryType::Land(LandType::Coasts(vec![
+                    "Spain (nc)".to_string(),
+                    "Spain (sc)".to_string(),
+                ])),
+            )
+            .unwrap();
+        let coasts = g.insert_declared_coasts(spain).unwrap();
+        let (nc, sc) = (coasts[0], coasts[1]);
+
+        assert_eq!(g.parent_province(nc), Some(spain));
+        assert_eq!(
+            g.coasts_of(spain).into_iter().collect::<HashSet<_>>(),
+            [nc, sc].into_iter().collect()
+        );
+
+        g.set_occupied(nc, true);
+        assert!(g.is_occupied(nc));
+        assert!(g.is_occupied(spain));
+        assert!(!g.is_occupied(sc));
+    }
+
+    #[test]
+    fn test_insert_declared_coasts_matches_the_provinces_coasts_list() {
+        let mut g = Graph::new();
+        let spain = g
+            .insert_named(
+                "Spain",
+                TerritoryType::Land(LandType::Coasts(vec![
+                    "Spain (nc)".to_string(),
+                    "Spain (sc)".to_string(),
+                ])),
+            )
+            .unwrap();
+
+        let coasts = g.insert_declared_coasts(spain).unwrap();
+
+        assert_eq!(
+            coasts.iter().map(|&id| &g.get(id).unwrap().name).collect::<Vec<_>>(),
+            vec!["Spain (nc)", "Spain (sc)"]
+        );
+    }
+
+    #[test]
+    fn test_insert_declared_coasts_is_empty_for_a_non_coast_province() {
+        let mut g = Graph::new();
+        let ankara = g
+            .insert_named("Ankara", TerritoryType::Land(LandType::Normal))
+            .unwrap();
+
+        assert_eq!(g.insert_declared_coasts(ankara).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_removing_parent_scrubs_coast_back_references() {
+        let mut g = Graph::new();
+        let spain = g
+            .insert_named(
+                "Spain",
+                TerritoryType::Land(LandType::Coasts(vec!["Spain (nc)".to_string()])),
+            )
+            .unwrap();
+        let nc = g.insert_declared_coasts(spain).unwrap()[0];
+
+        g.remove_node(spain);
+
+        assert_eq!(g.parent_province(nc), None);
+        assert_eq!(g.coasts_of(spain), Vec::new());
+    }
+
+    #[test]
+    fn test_to_dot_includes_nodes_and_edges() {
+        let g = gen_test_turkey();
+        let dot = g.to_dot();
+
+        assert!(dot.starts_with("graph diplomacy_map {"));
+        assert!(dot.contains("Constantinople"));
+        assert!(dot.contains("Ankara"));
+        assert!(dot.contains("Smyrna"));
+        assert!(dot.contains("--"));
+    }
+
+    #[test]
+    fn test_distance_respects_move_kind() {
+        let g = gen_test_turkey_region();
+        let con = g.node_by_name("Constantinople").unwrap();
+        let sev = g.node_by_name("Sevastopol").unwrap();
+        let smy = g.node_by_name("Smyrna").unwrap();
+        let arm = g.node_by_name("Armenia").unwrap();
+
+        // Army: Constantinople -> Ankara -> Armenia (land-only).
+        assert_eq!(g.distance(con, arm, MoveKind::Army), Some(2));
+
+        // Fleet: Constantinople -> Black Sea -> Sevastopol.
+        assert_eq!(g.distance(con, sev, MoveKind::Fleet), Some(2));
+
+        // Smyrna's sea access (the Eastern Mediterranean) never touches the
+        // Black Sea component, so no fleet route reaches it from Constantinople.
+        assert_eq!(g.distance(con, smy, MoveKind::Fleet), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_roundtrip_preserves_nodes_and_edges() {
+        let g = gen_test_turkey_region();
+
+        let json = serde_json::to_string(&g).unwrap();
+        let restored: Graph = serde_json::from_str(&json).unwrap();
+
+        let con = restored.node_by_name("Constantinople").unwrap();
+        let sev = restored.node_by_name("Sevastopol").unwrap();
+        assert_eq!(restored.distance(con, sev, MoveKind::Fleet), Some(2));
+    }
 }